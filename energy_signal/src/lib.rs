@@ -1,5 +1,8 @@
 mod signals;
 
+#[cfg(target_os = "linux")]
+pub mod monitor;
+
 #[no_mangle]
 pub extern "C" fn start_signal() -> i32 {
     signals::start_signal()
@@ -10,12 +13,38 @@ pub extern "C" fn stop_signal() {
     signals::stop_signal();
 }
 
+/// Opens a measurement region and hands back an opaque handle. The region
+/// stays open until the handle is passed to `end_measurement`, even if the
+/// caller unwinds through a panic in between.
+///
+/// # Safety
+/// The returned pointer must be passed to `end_measurement` exactly once,
+/// and to no other function.
+#[no_mangle]
+pub unsafe extern "C" fn begin_measurement() -> *mut signals::MeasurementGuard {
+    Box::into_raw(Box::new(signals::MeasurementGuard::new()))
+}
+
+/// Closes a measurement region opened by `begin_measurement`, raising the
+/// stop marker exactly once. A null handle is ignored.
+///
+/// # Safety
+/// `handle` must be a pointer returned by `begin_measurement` that has not
+/// already been passed here.
+#[no_mangle]
+pub unsafe extern "C" fn end_measurement(handle: *mut signals::MeasurementGuard) {
+    if handle.is_null() {
+        return;
+    }
+    drop(Box::from_raw(handle));
+}
+
 // JNI interface for Java
 #[cfg(target_os = "linux")]
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 pub mod jni {
-    use jni::objects::{JClass};
-    use jni::sys::jint;
+    use jni::objects::{JClass, JObject, JValue};
+    use jni::sys::{jint, jlong};
     use jni::JNIEnv;
 
     #[no_mangle]
@@ -33,4 +62,96 @@ pub mod jni {
     ) {
         crate::signals::stop_signal();
     }
+
+    /// Backs the `EnergyMeasurement` `AutoCloseable`: returns a handle for
+    /// `Java_EnergySignal_closeMeasurement` to close in its `close()`.
+    ///
+    /// # Safety
+    /// The returned handle must be passed to `closeMeasurement` exactly
+    /// once, and to no other function.
+    #[no_mangle]
+    pub unsafe extern "system" fn Java_EnergySignal_beginMeasurement(
+        _env: JNIEnv,
+        _class: JClass,
+    ) -> jlong {
+        Box::into_raw(Box::new(crate::signals::MeasurementGuard::new())) as jlong
+    }
+
+    /// Closes a measurement handle returned by `beginMeasurement`. Must be
+    /// called exactly once per handle, typically from `close()`.
+    ///
+    /// # Safety
+    /// `handle` must be a value returned by `beginMeasurement` that has not
+    /// already been passed here.
+    #[no_mangle]
+    pub unsafe extern "system" fn Java_EnergySignal_closeMeasurement(
+        _env: JNIEnv,
+        _class: JClass,
+        handle: jlong,
+    ) {
+        if handle == 0 {
+            return;
+        }
+        drop(Box::from_raw(handle as *mut crate::signals::MeasurementGuard));
+    }
+
+    /// How many times `startSignal` has been called so far (`stopSignal`
+    /// does not change it), so a JMH-style harness can drive its own loop
+    /// instead of relying solely on the native return value.
+    #[no_mangle]
+    pub extern "system" fn Java_EnergySignal_iterationCount(
+        _env: JNIEnv,
+        _class: JClass,
+    ) -> jint {
+        crate::signals::iteration_count() as jint
+    }
+
+    /// The configured `ITERATIONS` budget.
+    #[no_mangle]
+    pub extern "system" fn Java_EnergySignal_iterationBudget(
+        _env: JNIEnv,
+        _class: JClass,
+    ) -> jint {
+        crate::signals::iteration_budget() as jint
+    }
+
+    /// Invokes `callback.onProgress(int)`, clearing any pending exception
+    /// instead of propagating it: the callback may be null, have the wrong
+    /// signature, or throw, and unwinding a Rust panic across an
+    /// `extern "system"` boundary would abort the whole JVM.
+    fn notify_progress(env: &mut JNIEnv, callback: JObject, iteration: jint) {
+        let called = env.call_method(callback, "onProgress", "(I)V", &[JValue::Int(iteration)]);
+        if called.is_err() && env.exception_check().unwrap_or(false) {
+            let _ = env.exception_clear();
+        }
+    }
+
+    /// Like `startSignal`, but also invokes `callback.onProgress(int)` with
+    /// the current iteration index, so the Java side can log or update a
+    /// progress bar after the transition.
+    #[no_mangle]
+    pub extern "system" fn Java_EnergySignal_startSignalWithCallback(
+        mut env: JNIEnv,
+        _class: JClass,
+        callback: JObject,
+    ) -> jint {
+        let result = crate::signals::start_signal();
+        let iteration = crate::signals::iteration_count() as jint;
+        notify_progress(&mut env, callback, iteration);
+        result
+    }
+
+    /// Like `stopSignal`, but also invokes `callback.onProgress(int)` with
+    /// the current iteration index, so the Java side can log or update a
+    /// progress bar after the transition.
+    #[no_mangle]
+    pub extern "system" fn Java_EnergySignal_stopSignalWithCallback(
+        mut env: JNIEnv,
+        _class: JClass,
+        callback: JObject,
+    ) {
+        crate::signals::stop_signal();
+        let iteration = crate::signals::iteration_count() as jint;
+        notify_progress(&mut env, callback, iteration);
+    }
 }
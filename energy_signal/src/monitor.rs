@@ -0,0 +1,184 @@
+//! In-process observer for the `START_SIGNAL`/`STOP_SIGNAL` markers, for
+//! benchmark binaries that want to self-report region boundaries instead of
+//! relying entirely on an external collector.
+//!
+//! A signal mask is per-thread, and a thread inherits the mask of whichever
+//! thread created it. `MarkerStream::new` therefore only blocks the marker
+//! signals on the calling thread: a marker raised on any thread created
+//! *before* that call, or on a thread that never inherited the mask, is
+//! delivered to the regular `handle_signal` handler instead of the
+//! `signalfd` and will never show up here. Call `MarkerStream::new` (or
+//! `MarkerLog::spawn`) as early as possible in `main`, before spawning any
+//! thread that might raise a marker.
+
+use crate::signals::{start_signal_number, stop_signal_number};
+use nix::libc;
+use std::{
+    collections::VecDeque,
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Start,
+    Stop,
+}
+
+/// A single observed marker: which signal it was, when it arrived relative
+/// to the `MarkerStream`'s creation, and which iteration it belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct MarkerEvent {
+    pub marker: Marker,
+    pub timestamp: Duration,
+    pub iteration: usize,
+}
+
+/// Observes the marker signals via `signalfd` rather than a regular signal
+/// handler, so events can be drained synchronously instead of racing a
+/// handler running on an arbitrary thread.
+pub struct MarkerStream {
+    fd: OwnedFd,
+    epoch: Instant,
+    iteration: usize,
+}
+
+impl MarkerStream {
+    /// Blocks the marker signals on the calling thread and opens a
+    /// `signalfd` for them.
+    ///
+    /// The signal mask this installs is per-thread and is only inherited by
+    /// threads spawned *after* this call returns. Call this before spawning
+    /// any thread that raises a marker (ideally first thing in `main`), or
+    /// markers raised elsewhere will silently bypass the `signalfd` and hit
+    /// `handle_signal` instead.
+    pub fn new() -> io::Result<Self> {
+        unsafe {
+            let mut mask: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut mask);
+            libc::sigaddset(&mut mask, start_signal_number());
+            libc::sigaddset(&mut mask, stop_signal_number());
+
+            if libc::pthread_sigmask(libc::SIG_BLOCK, &mask, std::ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let fd = libc::signalfd(-1, &mask, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(MarkerStream {
+                fd: OwnedFd::from_raw_fd(fd),
+                epoch: Instant::now(),
+                iteration: 0,
+            })
+        }
+    }
+
+    /// Reads one queued signalfd event without blocking. Returns `Ok(None)`
+    /// when nothing is currently pending.
+    fn read_one(&mut self) -> io::Result<Option<MarkerEvent>> {
+        let mut info: libc::signalfd_siginfo = unsafe { std::mem::zeroed() };
+        let n = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                &mut info as *mut _ as *mut libc::c_void,
+                std::mem::size_of::<libc::signalfd_siginfo>(),
+            )
+        };
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            return match err.kind() {
+                io::ErrorKind::WouldBlock => Ok(None),
+                _ => Err(err),
+            };
+        }
+
+        let marker = if info.ssi_signo as libc::c_int == start_signal_number() {
+            self.iteration += 1;
+            Marker::Start
+        } else {
+            Marker::Stop
+        };
+
+        Ok(Some(MarkerEvent {
+            marker,
+            timestamp: self.epoch.elapsed(),
+            iteration: self.iteration,
+        }))
+    }
+
+    /// Drains every marker currently queued on the signalfd, in order.
+    /// Real-time marker signals (`SIGRTMIN`..`SIGRTMAX`) are queued, so
+    /// several start/stop pairs raised between polls all come back here.
+    /// Standard signals such as the default `SIGUSR1`/`SIGUSR2` have at
+    /// most one pending instance each, so repeats raised between polls
+    /// come back as a single event.
+    pub fn pending(&mut self) -> io::Result<Vec<MarkerEvent>> {
+        let mut events = Vec::new();
+        while let Some(event) = self.read_one()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// Blocks until the next marker is raised.
+    pub fn blocking_next(&mut self) -> io::Result<MarkerEvent> {
+        loop {
+            if let Some(event) = self.read_one()? {
+                return Ok(event);
+            }
+
+            let mut pfd = libc::pollfd {
+                fd: self.fd.as_raw_fd() as RawFd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut pfd, 1, -1) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+}
+
+/// Background-thread companion to `MarkerStream`: drains markers as they
+/// arrive and keeps the most recent `capacity` of them in memory, so a
+/// benchmark can poll for start/stop pairs from anywhere without owning the
+/// stream itself.
+pub struct MarkerLog {
+    events: Arc<Mutex<VecDeque<MarkerEvent>>>,
+}
+
+impl MarkerLog {
+    /// Spawns the background thread and starts appending to the ring
+    /// buffer immediately. `capacity` is clamped to at least 1.
+    pub fn spawn(capacity: usize) -> io::Result<Self> {
+        let capacity = capacity.max(1);
+        let mut stream = MarkerStream::new()?;
+        let events = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        let log = MarkerLog {
+            events: events.clone(),
+        };
+
+        thread::spawn(move || {
+            while let Ok(event) = stream.blocking_next() {
+                let mut events = events.lock().unwrap();
+                while events.len() >= capacity {
+                    events.pop_front();
+                }
+                events.push_back(event);
+            }
+        });
+
+        Ok(log)
+    }
+
+    /// Snapshots the events currently held in the ring buffer, oldest first.
+    pub fn snapshot(&self) -> Vec<MarkerEvent> {
+        self.events.lock().unwrap().iter().copied().collect()
+    }
+}
@@ -1,29 +1,128 @@
 use once_cell::sync::Lazy;
 use std::{
     env,
+    os::raw::{c_int, c_void},
+    str::FromStr,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicI32, AtomicUsize, Ordering},
         Once,
     },
 };
 use nix::libc;
-use nix::sys::signal::{raise, sigaction, SigAction, SigHandler, SaFlags, SigSet, Signal};
+use nix::sys::signal::Signal;
 
-extern "C" fn handle_signal(_: libc::c_int) {}
+/// The previously-installed handler for one marker signal, captured by
+/// `install_handler` so `handle_signal` can chain to it. `addr` holds
+/// `SIG_DFL`, `SIG_IGN`, or a real `sa_handler`/`sa_sigaction` function
+/// pointer; `flags` records whether that pointer expects the `SA_SIGINFO`
+/// three-argument form.
+struct PrevHandler {
+    addr: AtomicUsize,
+    flags: AtomicI32,
+}
+
+impl PrevHandler {
+    const fn new() -> Self {
+        PrevHandler {
+            addr: AtomicUsize::new(libc::SIG_DFL),
+            flags: AtomicI32::new(0),
+        }
+    }
+}
+
+static PREV_START: PrevHandler = PrevHandler::new();
+static PREV_STOP: PrevHandler = PrevHandler::new();
+
+/// Marker handler installed for both signals. Chains to whatever handler
+/// the target program had registered before us, so the markers can coexist
+/// with a benchmark that uses the same signal for its own purposes. Uses
+/// only async-signal-safe operations: atomic loads and a direct call
+/// through the saved function pointer.
+extern "C" fn handle_signal(signum: c_int, info: *mut libc::siginfo_t, ctx: *mut c_void) {
+    let prev = if signum == *START_SIGNAL {
+        &PREV_START
+    } else {
+        &PREV_STOP
+    };
+
+    let addr = prev.addr.load(Ordering::SeqCst);
+    if addr == libc::SIG_DFL || addr == libc::SIG_IGN {
+        return;
+    }
+
+    if prev.flags.load(Ordering::SeqCst) & libc::SA_SIGINFO != 0 {
+        let handler: extern "C" fn(c_int, *mut libc::siginfo_t, *mut c_void) =
+            unsafe { std::mem::transmute(addr) };
+        handler(signum, info, ctx);
+    } else {
+        let handler: extern "C" fn(c_int) = unsafe { std::mem::transmute(addr) };
+        handler(signum);
+    }
+}
 
 static HANDLER_INIT: Once = Once::new();
 
-fn init_signal_handler() {
-    HANDLER_INIT.call_once(|| {
-        let sa = SigAction::new(
-            SigHandler::Handler(handle_signal),
-            SaFlags::empty(),
-            SigSet::empty(),
-        );
-        unsafe {
-            sigaction(Signal::SIGUSR1, &sa).unwrap();
-            sigaction(Signal::SIGUSR2, &sa).unwrap();
+/// Resolves the raw signal number for `var`, accepting either a symbolic
+/// name (`"SIGUSR1"`, `"SIGRTMIN+3"`) or a raw integer, and falling back to
+/// `default` when the variable is unset.
+fn resolve_signal(var: &str, default: c_int) -> c_int {
+    match env::var(var) {
+        Ok(value) => {
+            parse_signal(&value).unwrap_or_else(|| panic!("{var}: invalid signal {value:?}"))
         }
+        Err(_) => default,
+    }
+}
+
+fn parse_signal(spec: &str) -> Option<c_int> {
+    let spec = spec.trim();
+    let signum = if let Some(offset) = spec.strip_prefix("SIGRTMIN+") {
+        libc::SIGRTMIN() + offset.parse::<c_int>().ok()?
+    } else if spec == "SIGRTMIN" {
+        libc::SIGRTMIN()
+    } else if let Some(offset) = spec.strip_prefix("SIGRTMAX-") {
+        libc::SIGRTMAX() - offset.parse::<c_int>().ok()?
+    } else if spec == "SIGRTMAX" {
+        libc::SIGRTMAX()
+    } else if let Ok(signal) = Signal::from_str(spec) {
+        signal as c_int
+    } else {
+        spec.parse::<c_int>().ok()?
+    };
+
+    // `NSIG` (one past the highest valid signal number) isn't exposed by
+    // the libc crate and varies by arch (65 on x86/glibc, 129 on MIPS), so
+    // derive the upper bound from `SIGRTMAX()` rather than hardcoding it.
+    let nsig = libc::SIGRTMAX() + 1;
+    (signum > 0 && signum < nsig).then_some(signum)
+}
+
+static START_SIGNAL: Lazy<c_int> =
+    Lazy::new(|| resolve_signal("START_SIGNAL", Signal::SIGUSR1 as c_int));
+static STOP_SIGNAL: Lazy<c_int> =
+    Lazy::new(|| resolve_signal("STOP_SIGNAL", Signal::SIGUSR2 as c_int));
+
+/// Installs the marker handler for `signum`, recording whatever handler was
+/// previously registered into `prev` so `handle_signal` can chain to it.
+/// Real-time signals (and any signal outside the range
+/// `nix::sys::signal::Signal` enumerates) have no `Signal` variant, so this
+/// goes through raw libc rather than `nix::sys::signal::sigaction`.
+unsafe fn install_handler(signum: c_int, prev: &PrevHandler) {
+    let mut sa: libc::sigaction = std::mem::zeroed();
+    sa.sa_sigaction = handle_signal as *const () as usize;
+    sa.sa_flags = libc::SA_SIGINFO;
+    libc::sigemptyset(&mut sa.sa_mask);
+
+    let mut old: libc::sigaction = std::mem::zeroed();
+    libc::sigaction(signum, &sa, &mut old);
+    prev.addr.store(old.sa_sigaction, Ordering::SeqCst);
+    prev.flags.store(old.sa_flags, Ordering::SeqCst);
+}
+
+fn init_signal_handler() {
+    HANDLER_INIT.call_once(|| unsafe {
+        install_handler(*START_SIGNAL, &PREV_START);
+        install_handler(*STOP_SIGNAL, &PREV_STOP);
     });
 }
 
@@ -41,7 +140,7 @@ pub fn start_signal() -> i32 {
 
     let curr = ITERATION_COUNT.fetch_add(1, Ordering::SeqCst);
     if curr < *ITERATIONS {
-        raise(Signal::SIGUSR1).unwrap();
+        unsafe { libc::raise(*START_SIGNAL) };
         1
     } else {
         0
@@ -53,6 +152,61 @@ pub fn stop_signal() {
 
     let curr = ITERATION_COUNT.load(Ordering::SeqCst);
     if curr > 0 && curr <= *ITERATIONS {
-        raise(Signal::SIGUSR2).unwrap();
+        unsafe { libc::raise(*STOP_SIGNAL) };
+    }
+}
+
+/// RAII measurement region: raises the start marker on construction and
+/// raises the stop marker exactly once on drop, so a panic or early return
+/// between the two calls can never leave the region open. Becomes a no-op
+/// once the `ITERATIONS` budget is exhausted, same as a bare `start_signal`/
+/// `stop_signal` pair would.
+pub struct MeasurementGuard {
+    active: bool,
+}
+
+impl MeasurementGuard {
+    pub fn new() -> Self {
+        MeasurementGuard {
+            active: start_signal() == 1,
+        }
+    }
+}
+
+impl Default for MeasurementGuard {
+    fn default() -> Self {
+        Self::new()
     }
 }
+
+impl Drop for MeasurementGuard {
+    fn drop(&mut self) {
+        if self.active {
+            unsafe { libc::raise(*STOP_SIGNAL) };
+        }
+    }
+}
+
+/// The resolved start marker signal number, for callers (such as the
+/// `monitor` module) that need to listen for it themselves.
+pub(crate) fn start_signal_number() -> c_int {
+    *START_SIGNAL
+}
+
+/// The resolved stop marker signal number, for callers (such as the
+/// `monitor` module) that need to listen for it themselves.
+pub(crate) fn stop_signal_number() -> c_int {
+    *STOP_SIGNAL
+}
+
+/// How many times `start_signal` has been called so far (including calls
+/// past the `ITERATIONS` budget; `stop_signal` does not change it), for
+/// callers (such as the JNI bindings) that want to report progress.
+pub(crate) fn iteration_count() -> usize {
+    ITERATION_COUNT.load(Ordering::SeqCst)
+}
+
+/// The configured `ITERATIONS` budget.
+pub(crate) fn iteration_budget() -> usize {
+    *ITERATIONS
+}